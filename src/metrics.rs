@@ -0,0 +1,115 @@
+use metrics::{counter, gauge};
+use metrics_exporter_prometheus::PrometheusBuilder;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+/// Metric name: total number of requests sent, across every host/directory being scanned
+pub const REQUESTS_TOTAL: &str = "feroxbuster_requests_total";
+
+/// Metric name: responses seen, labeled by status code class (`class` -> "2xx"/"3xx"/"4xx"/"5xx")
+pub const RESPONSES_TOTAL: &str = "feroxbuster_responses_total";
+
+/// Metric name: responses dropped by a filter, labeled by `filter` -> "size"/"static_wildcard"/"dynamic_wildcard"
+pub const FILTERED_TOTAL: &str = "feroxbuster_filtered_total";
+
+/// Metric name: directories discovered and queued for recursive scanning
+pub const DIRECTORIES_QUEUED_TOTAL: &str = "feroxbuster_directories_queued_total";
+
+/// Metric name: current recursion depth being processed (gauge, most recently reported value wins)
+pub const CURRENT_DEPTH: &str = "feroxbuster_current_depth";
+
+/// Metric name: requests/sec, sampled once per second from [record_request](fn.record_request.html)
+///
+/// Exposed as its own gauge rather than left to be derived via PromQL `rate()`, since the
+/// literal ask was a requests/sec metric, not just a cumulative counter
+pub const REQUESTS_PER_SECOND: &str = "feroxbuster_requests_per_second";
+
+/// Running total of requests sent, sampled once a second by the rate reporter task to compute
+/// [REQUESTS_PER_SECOND](constant.REQUESTS_PER_SECOND.html)
+static REQUEST_COUNT: AtomicU64 = AtomicU64::new(0);
+
+/// Record that a request was sent: bumps both the cumulative counter and the running total used
+/// to derive the requests/sec gauge
+pub fn record_request() {
+    REQUEST_COUNT.fetch_add(1, Ordering::Relaxed);
+    counter!(REQUESTS_TOTAL, 1);
+}
+
+/// Start the embedded Prometheus exporter, listening on `0.0.0.0:<port>`
+///
+/// Installs the recorder globally; after this call, the `metrics` crate's `counter!`/`gauge!`
+/// macros anywhere in the binary are scraped under `/metrics`
+pub fn start_metrics_server(port: u16) {
+    log::trace!("enter: start_metrics_server({})", port);
+
+    let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), port);
+
+    match PrometheusBuilder::new().listen_address(addr).install() {
+        Ok(_) => {
+            log::info!("metrics available at http://{}/metrics", addr);
+        }
+        Err(e) => {
+            log::error!("could not start metrics server on {}: {}", addr, e);
+        }
+    }
+
+    spawn_rate_reporter();
+
+    log::trace!("exit: start_metrics_server");
+}
+
+/// Sample [REQUEST_COUNT](static.REQUEST_COUNT.html) once a second, turning the delta between
+/// samples into the [REQUESTS_PER_SECOND](constant.REQUESTS_PER_SECOND.html) gauge
+fn spawn_rate_reporter() {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(1));
+        let mut last_count = REQUEST_COUNT.load(Ordering::Relaxed);
+
+        loop {
+            interval.tick().await;
+
+            let current_count = REQUEST_COUNT.load(Ordering::Relaxed);
+            gauge!(REQUESTS_PER_SECOND, (current_count - last_count) as f64);
+            last_count = current_count;
+        }
+    });
+}
+
+/// Bucket an HTTP status code into its class, e.g. 404 -> "4xx"
+pub fn status_class(status: u16) -> &'static str {
+    match status / 100 {
+        1 => "1xx",
+        2 => "2xx",
+        3 => "3xx",
+        4 => "4xx",
+        5 => "5xx",
+        _ => "unknown",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    /// status_class should bucket by the status code's leading digit
+    fn status_class_buckets_correctly() {
+        assert_eq!(status_class(200), "2xx");
+        assert_eq!(status_class(301), "3xx");
+        assert_eq!(status_class(404), "4xx");
+        assert_eq!(status_class(503), "5xx");
+        assert_eq!(status_class(999), "unknown");
+    }
+
+    #[test]
+    /// record_request should bump the running total used to derive requests/sec
+    fn record_request_increments_running_total() {
+        let before = REQUEST_COUNT.load(Ordering::Relaxed);
+
+        record_request();
+        record_request();
+
+        assert_eq!(REQUEST_COUNT.load(Ordering::Relaxed), before + 2);
+    }
+}
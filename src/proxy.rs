@@ -0,0 +1,206 @@
+use reqwest::Client;
+use std::sync::atomic::{AtomicU32, AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+
+/// number of consecutive connection failures before a proxy is pulled out of rotation
+const UNHEALTHY_THRESHOLD: u32 = 3;
+
+/// how long an unhealthy proxy sits out before being re-probed
+const REPROBE_INTERVAL: Duration = Duration::from_secs(30);
+
+/// A single proxy's pre-configured client, paired with simple health tracking
+struct ProxyClient {
+    /// the proxy's address, kept around for logging
+    address: String,
+
+    /// client configured to route all traffic through this proxy
+    client: Client,
+
+    /// consecutive connection failures seen through this proxy
+    failures: AtomicU32,
+
+    /// last time this proxy was marked unhealthy, used to gate re-probing
+    marked_unhealthy_at: Mutex<Option<Instant>>,
+}
+
+impl ProxyClient {
+    /// Returns true if this proxy is currently eligible for rotation, either because it's
+    /// healthy or because enough time has passed to give it another try
+    async fn is_available(&self) -> bool {
+        if self.failures.load(Ordering::Relaxed) < UNHEALTHY_THRESHOLD {
+            return true;
+        }
+
+        match *self.marked_unhealthy_at.lock().await {
+            Some(since) => since.elapsed() >= REPROBE_INTERVAL,
+            None => true,
+        }
+    }
+
+    /// Record a successful request, resetting the failure count
+    fn record_success(&self) {
+        self.failures.store(0, Ordering::Relaxed);
+    }
+
+    /// Record a failed (connection-level) request; once the threshold is crossed, stamp the
+    /// time so `is_available` knows when to let it back into rotation
+    async fn record_failure(&self) {
+        let failures = self.failures.fetch_add(1, Ordering::Relaxed) + 1;
+
+        if failures >= UNHEALTHY_THRESHOLD {
+            // re-stamp on every failure at/above the threshold (not just the exact transition),
+            // so a proxy that's re-probed and fails again is immediately re-quarantined instead
+            // of being considered available forever after its first cooldown expires
+            log::warn!(
+                "proxy {} marked unhealthy after {} consecutive failures",
+                self.address,
+                failures
+            );
+            *self.marked_unhealthy_at.lock().await = Some(Instant::now());
+        }
+    }
+}
+
+/// Pool of pre-configured `reqwest::Client`s, one per `--proxy-list` entry, rotated round-robin
+/// across requests so scan traffic is spread across multiple egress IPs
+pub struct ProxyPool {
+    /// every configured proxy client
+    clients: Vec<ProxyClient>,
+
+    /// index of the next client to hand out, wrapped modulo `clients.len()`
+    next: AtomicUsize,
+}
+
+impl ProxyPool {
+    /// Build a `ProxyPool` from a list of proxy addresses (`http://...` or `socks5://...`)
+    ///
+    /// Proxies that fail to parse are logged and skipped rather than aborting the whole pool
+    pub fn new(proxies: &[String]) -> Self {
+        log::trace!("enter: ProxyPool::new({:?})", proxies);
+
+        let clients = proxies
+            .iter()
+            .filter_map(|address| match reqwest::Proxy::all(address) {
+                Ok(proxy) => match Client::builder().proxy(proxy).build() {
+                    Ok(client) => Some(ProxyClient {
+                        address: address.clone(),
+                        client,
+                        failures: AtomicU32::new(0),
+                        marked_unhealthy_at: Mutex::new(None),
+                    }),
+                    Err(e) => {
+                        log::error!("could not build client for proxy {}: {}", address, e);
+                        None
+                    }
+                },
+                Err(e) => {
+                    log::error!("could not parse proxy {}: {}", address, e);
+                    None
+                }
+            })
+            .collect();
+
+        ProxyPool {
+            clients,
+            next: AtomicUsize::new(0),
+        }
+    }
+
+    /// Returns true if no proxies were successfully configured
+    pub fn is_empty(&self) -> bool {
+        self.clients.is_empty()
+    }
+
+    /// Pick the next available client via round-robin, skipping over any proxy that's currently
+    /// marked unhealthy. Returns `None` if every proxy in the pool is unhealthy. The returned
+    /// index should be passed back into [report_outcome](#method.report_outcome) once the
+    /// request completes, so health tracking stays attributed to the right proxy.
+    pub async fn next_client(&self) -> Option<(usize, Client)> {
+        let len = self.clients.len();
+
+        for _ in 0..len {
+            let idx = self.next.fetch_add(1, Ordering::Relaxed) % len;
+            let candidate = &self.clients[idx];
+
+            if candidate.is_available().await {
+                return Some((idx, candidate.client.clone()));
+            }
+        }
+
+        log::warn!("every proxy in the pool is currently unhealthy");
+        None
+    }
+
+    /// Report the outcome of a request made through the client at `idx`, as returned by
+    /// [next_client](#method.next_client)
+    pub async fn report_outcome(&self, idx: usize, succeeded: bool) {
+        let candidate = &self.clients[idx];
+
+        if succeeded {
+            candidate.record_success();
+        } else {
+            candidate.record_failure().await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    /// a pool built from zero proxy addresses should be empty
+    fn proxy_pool_with_no_addresses_is_empty() {
+        let pool = ProxyPool::new(&[]);
+        assert!(pool.is_empty());
+    }
+
+    #[test]
+    /// a malformed proxy address should be skipped rather than included in the pool
+    fn proxy_pool_skips_unparseable_addresses() {
+        let pool = ProxyPool::new(&[String::from("not a valid proxy url")]);
+        assert!(pool.is_empty());
+    }
+
+    #[tokio::test]
+    /// a proxy that racks up enough consecutive failures should be skipped by next_client until
+    /// it's given a chance to be re-probed
+    async fn unhealthy_proxy_is_skipped_in_rotation() {
+        let pool = ProxyPool::new(&[
+            String::from("http://127.0.0.1:10001"),
+            String::from("http://127.0.0.1:10002"),
+        ]);
+
+        let (bad_idx, _) = pool.next_client().await.unwrap();
+
+        for _ in 0..UNHEALTHY_THRESHOLD {
+            pool.report_outcome(bad_idx, false).await;
+        }
+
+        for _ in 0..4 {
+            let (idx, _) = pool.next_client().await.unwrap();
+            assert_ne!(idx, bad_idx);
+        }
+    }
+
+    #[tokio::test]
+    /// failing a re-probe should re-quarantine a proxy rather than leaving its stale
+    /// `marked_unhealthy_at` timestamp to expire and admit it forever afterwards
+    async fn failed_reprobe_refreshes_quarantine() {
+        let pool = ProxyPool::new(&[String::from("http://127.0.0.1:10001")]);
+
+        for _ in 0..UNHEALTHY_THRESHOLD {
+            pool.report_outcome(0, false).await;
+        }
+
+        // simulate the cooldown having expired and a re-probe being attempted, which fails again
+        *pool.clients[0].marked_unhealthy_at.lock().await =
+            Some(Instant::now() - REPROBE_INTERVAL);
+        assert!(pool.clients[0].is_available().await);
+
+        pool.report_outcome(0, false).await;
+
+        assert!(!pool.clients[0].is_available().await);
+    }
+}
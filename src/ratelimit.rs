@@ -0,0 +1,120 @@
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use tokio::time::{delay_for, Instant};
+
+/// Shared, `Arc`-wrapped token bucket used to cap requests/sec across every recursive
+/// [scan_url](../scanner/fn.scan_url.html) invocation
+///
+/// Built once at startup (from `--rate-limit`) and cloned into every producer task, so the limit
+/// is global rather than per-directory
+pub struct RateLimiter {
+    /// inner state, guarded by a mutex since `acquire` both reads and mutates token count
+    inner: Mutex<TokenBucket>,
+}
+
+/// Inner, mutable state of a [RateLimiter](struct.RateLimiter.html)
+struct TokenBucket {
+    /// number of tokens currently available
+    tokens: f64,
+
+    /// last time the bucket was refilled
+    last_refill: Instant,
+
+    /// maximum number of tokens the bucket can hold (burst size)
+    capacity: f64,
+
+    /// tokens refilled per second, i.e. the requests/sec cap
+    rate: f64,
+}
+
+impl RateLimiter {
+    /// Create a new `RateLimiter` with the given requests/sec rate and burst capacity
+    ///
+    /// The bucket starts full, allowing an initial burst up to `capacity`
+    pub fn new(rate: f64, capacity: f64) -> Self {
+        log::trace!("enter: RateLimiter::new({}, {})", rate, capacity);
+
+        let limiter = RateLimiter {
+            inner: Mutex::new(TokenBucket {
+                tokens: capacity,
+                last_refill: Instant::now(),
+                capacity,
+                rate,
+            }),
+        };
+
+        log::trace!("exit: RateLimiter::new -> {:?}", limiter);
+        limiter
+    }
+
+    /// Wrap `self` in an `Arc`, ready to be shared across tasks
+    pub fn shared(rate: f64, capacity: f64) -> Arc<Self> {
+        Arc::new(Self::new(rate, capacity))
+    }
+
+    /// Draw a single token from the bucket, sleeping first if none are currently available
+    ///
+    /// Refills the bucket based on elapsed time since the last refill, then either takes a
+    /// token immediately or sleeps just long enough for one to become available
+    pub async fn acquire(&self) {
+        log::trace!("enter: RateLimiter::acquire");
+
+        let wait_secs = {
+            // scope the lock so the guard is dropped before any sleep below; otherwise every
+            // other acquire() caller would block for the full sleep, serializing the scan
+            let mut bucket = self.inner.lock().await;
+
+            let now = Instant::now();
+            let elapsed = now.duration_since(bucket.last_refill).as_secs_f64();
+
+            bucket.tokens = (bucket.tokens + elapsed * bucket.rate).min(bucket.capacity);
+            bucket.last_refill = now;
+
+            // reserve this caller's token immediately, letting tokens go negative, instead of
+            // flooring at 0. otherwise every concurrent caller sees the same `tokens < 1.0`
+            // snapshot, computes the same wait, and they all proceed together once it elapses -
+            // bursting up to `threads` requests per `1/rate` window instead of bounding the
+            // aggregate rate. going negative means each caller's wait reflects the debt already
+            // reserved by callers ahead of it, so their sleeps stack instead of overlapping.
+            bucket.tokens -= 1.0;
+
+            if bucket.tokens < 0.0 {
+                Some(-bucket.tokens / bucket.rate)
+            } else {
+                None
+            }
+        };
+
+        if let Some(wait_secs) = wait_secs {
+            log::debug!("rate limit reached, sleeping {}s", wait_secs);
+            delay_for(std::time::Duration::from_secs_f64(wait_secs)).await;
+        }
+
+        log::trace!("exit: RateLimiter::acquire");
+    }
+}
+
+impl std::fmt::Debug for RateLimiter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RateLimiter").finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    /// a freshly created bucket should allow up to `capacity` acquisitions without blocking for
+    /// long, since it starts full
+    async fn rate_limiter_starts_full() {
+        let limiter = RateLimiter::new(10.0, 5.0);
+
+        for _ in 0..5 {
+            limiter.acquire().await;
+        }
+
+        let remaining = limiter.inner.lock().await.tokens;
+        assert!(remaining < 1.0);
+    }
+}
@@ -0,0 +1,233 @@
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+
+/// Persistent, periodically-flushed record of scan progress used by `--resume-from`
+///
+/// Tracks which directories have already been dispatched through the recursion channel and,
+/// per directory, which words have already been requested. On startup, a state file whose
+/// `config_hash` matches the current wordlist/extensions is used to skip completed work instead
+/// of re-issuing every request.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ScanState {
+    /// hash of the wordlist + extensions used to produce this state, used to invalidate resume
+    /// attempts against a mismatched configuration
+    config_hash: String,
+
+    /// every directory that has been sent through the recursion channel so far
+    dispatched_directories: HashSet<String>,
+
+    /// words already requested, keyed by the directory (target url) they were requested against
+    progress: HashMap<String, HashSet<String>>,
+}
+
+impl ScanState {
+    /// Create a fresh, empty `ScanState` tied to the given configuration hash
+    pub fn new(config_hash: String) -> Self {
+        ScanState {
+            config_hash,
+            dispatched_directories: HashSet::new(),
+            progress: HashMap::new(),
+        }
+    }
+
+    /// Returns true if `other_hash` doesn't match the hash this state was created with, meaning
+    /// the wordlist or extensions have changed since the state file was written
+    pub fn is_stale(&self, other_hash: &str) -> bool {
+        self.config_hash != other_hash
+    }
+
+    /// Record that `directory` has been dispatched for scanning
+    pub fn mark_dispatched(&mut self, directory: &str) {
+        self.dispatched_directories.insert(directory.to_owned());
+    }
+
+    /// Returns true if `directory` was already dispatched in a previous run
+    pub fn is_dispatched(&self, directory: &str) -> bool {
+        self.dispatched_directories.contains(directory)
+    }
+
+    /// Record that `word` has already been requested against `directory`
+    pub fn mark_word_done(&mut self, directory: &str, word: &str) {
+        self.progress
+            .entry(directory.to_owned())
+            .or_insert_with(HashSet::new)
+            .insert(word.to_owned());
+    }
+
+    /// Returns true if `word` has already been requested against `directory` in a previous run
+    pub fn is_word_done(&self, directory: &str, word: &str) -> bool {
+        self.progress
+            .get(directory)
+            .map_or(false, |words| words.contains(word))
+    }
+
+    /// Returns true once every word in `wordlist` has already been requested against `directory`
+    pub fn is_directory_complete(&self, directory: &str, wordlist: &HashSet<String>) -> bool {
+        match self.progress.get(directory) {
+            Some(done) => wordlist.iter().all(|word| done.contains(word)),
+            None => false,
+        }
+    }
+}
+
+/// Hash the wordlist (order-independent) and extensions so resume attempts against a different
+/// configuration can be detected and refused
+pub fn hash_config(wordlist: &HashSet<String>, extensions: &[String]) -> String {
+    let mut words: Vec<&String> = wordlist.iter().collect();
+    words.sort();
+
+    let mut hasher = DefaultHasher::new();
+    words.hash(&mut hasher);
+    extensions.hash(&mut hasher);
+
+    format!("{:x}", hasher.finish())
+}
+
+/// Serialize `state` to a `String`, ready to be written to disk
+///
+/// Split out from [save_state](fn.save_state.html) so callers holding `state` behind a mutex
+/// can serialize while the lock is held and then drop the guard before the (comparatively slow)
+/// disk write
+pub fn serialize(state: &ScanState) -> String {
+    serde_json::to_string(state).unwrap_or_default()
+}
+
+/// Serialize `state` to `path`, overwriting any existing file
+pub async fn save_state(path: &str, state: &ScanState) -> tokio::io::Result<()> {
+    log::trace!("enter: save_state({}, {:?})", path, state);
+
+    tokio::fs::write(path, serialize(state)).await
+}
+
+/// Deserialize a [ScanState](struct.ScanState.html) from `path`, returning `None` on any error
+/// (missing file, bad json, etc.) so callers can simply fall back to a fresh scan
+pub async fn load_state(path: &str) -> Option<ScanState> {
+    log::trace!("enter: load_state({})", path);
+
+    let contents = tokio::fs::read_to_string(path).await.ok()?;
+
+    serde_json::from_str(&contents).ok()
+}
+
+/// Load the state file at `path`, refusing (and starting fresh) if it doesn't match
+/// `config_hash`, i.e. the wordlist or extensions used to produce it have changed
+///
+/// This is the entry point `--resume-from` should go through, as it's the only place that
+/// actually enforces the "invalidate resume on mismatched config" requirement
+pub async fn load_or_create(path: &str, config_hash: &str) -> ScanState {
+    log::trace!("enter: load_or_create({}, {})", path, config_hash);
+
+    match load_state(path).await {
+        Some(state) if !state.is_stale(config_hash) => {
+            log::info!("resuming previous scan from {}", path);
+            state
+        }
+        Some(_) => {
+            log::warn!(
+                "resume state in {} was built from a different wordlist/extensions; refusing to resume, starting fresh",
+                path
+            );
+            ScanState::new(config_hash.to_owned())
+        }
+        None => {
+            log::debug!("no existing resume state at {}, starting fresh", path);
+            ScanState::new(config_hash.to_owned())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    /// hashing the same words in a different order should produce the same hash
+    fn hash_config_is_order_independent() {
+        let mut forward = HashSet::new();
+        forward.insert(String::from("admin"));
+        forward.insert(String::from("login"));
+
+        let mut backward = HashSet::new();
+        backward.insert(String::from("login"));
+        backward.insert(String::from("admin"));
+
+        assert_eq!(
+            hash_config(&forward, &[]),
+            hash_config(&backward, &[])
+        );
+    }
+
+    #[test]
+    /// changing the extensions used should change the resulting hash
+    fn hash_config_differs_with_different_extensions() {
+        let mut words = HashSet::new();
+        words.insert(String::from("admin"));
+
+        let no_ext = hash_config(&words, &[]);
+        let with_ext = hash_config(&words, &[String::from("php")]);
+
+        assert_ne!(no_ext, with_ext);
+    }
+
+    #[test]
+    /// a directory is only complete once every word in the wordlist has been marked done
+    fn is_directory_complete_requires_every_word() {
+        let mut wordlist = HashSet::new();
+        wordlist.insert(String::from("admin"));
+        wordlist.insert(String::from("login"));
+
+        let mut state = ScanState::new(String::from("hash"));
+        state.mark_word_done("http://localhost/", "admin");
+
+        assert!(!state.is_directory_complete("http://localhost/", &wordlist));
+
+        state.mark_word_done("http://localhost/", "login");
+
+        assert!(state.is_directory_complete("http://localhost/", &wordlist));
+    }
+
+    #[tokio::test]
+    /// a state file whose hash doesn't match the current config should be refused: the returned
+    /// state must be fresh (no dispatched directories carried over), not the stale one on disk
+    async fn load_or_create_refuses_mismatched_hash() {
+        let dir = std::env::temp_dir();
+        let path = dir.join(format!(
+            "ferox-resume-test-{}.json",
+            std::process::id()
+        ));
+        let path = path.to_str().unwrap();
+
+        let mut stale = ScanState::new(String::from("old-hash"));
+        stale.mark_dispatched("http://localhost/admin/");
+        save_state(path, &stale).await.unwrap();
+
+        let loaded = load_or_create(path, "new-hash").await;
+
+        assert!(!loaded.is_dispatched("http://localhost/admin/"));
+
+        tokio::fs::remove_file(path).await.ok();
+    }
+
+    #[tokio::test]
+    /// a state file whose hash matches the current config should be resumed as-is
+    async fn load_or_create_resumes_matching_hash() {
+        let dir = std::env::temp_dir();
+        let path = dir.join(format!(
+            "ferox-resume-test-match-{}.json",
+            std::process::id()
+        ));
+        let path = path.to_str().unwrap();
+
+        let mut matching = ScanState::new(String::from("same-hash"));
+        matching.mark_dispatched("http://localhost/admin/");
+        save_state(path, &matching).await.unwrap();
+
+        let loaded = load_or_create(path, "same-hash").await;
+
+        assert!(loaded.is_dispatched("http://localhost/admin/"));
+
+        tokio::fs::remove_file(path).await.ok();
+    }
+}
@@ -1,27 +1,121 @@
 use crate::config::{CONFIGURATION, PROGRESS_BAR, PROGRESS_PRINTER};
 use crate::heuristics::WildcardFilter;
+use crate::metrics as feroxmetrics;
+use crate::proxy::ProxyPool;
+use crate::ratelimit::RateLimiter;
+use crate::resume::ScanState;
 use crate::utils::{
     ferox_print, format_url, get_current_depth, get_url_path_length, make_request, status_colorizer,
 };
-use crate::{heuristics, progress};
+use crate::{heuristics, progress, resume};
+use ::metrics::{counter, gauge};
 use futures::future::{BoxFuture, FutureExt};
 use futures::{stream, StreamExt};
-use reqwest::{Response, Url};
+use rand::Rng;
+use reqwest::{Response, StatusCode, Url};
+use serde::Serialize;
 use std::collections::HashSet;
 use std::convert::TryInto;
 use std::ops::Deref;
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::fs;
 use tokio::io::{self, AsyncWriteExt};
 use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
 use tokio::task::JoinHandle;
+use tokio::time::delay_for;
+
+/// base duration used when computing exponential backoff between retries (~100ms)
+const RETRY_BACKOFF_BASE: Duration = Duration::from_millis(100);
+
+/// upper bound on how long a single backoff sleep is allowed to last (~10s)
+const RETRY_BACKOFF_CAP: Duration = Duration::from_secs(10);
+
+/// Serializable representation of a single scan result
+///
+/// Used by both reporters when `--json` is passed, one of these is emitted per line (JSONL)
+#[derive(Debug, Serialize)]
+struct FeroxResponse {
+    /// the full url that was requested
+    url: String,
+
+    /// the directory containing the url's path, i.e. its parent
+    parent: String,
+
+    /// http status code of the response
+    status: u16,
+
+    /// Content-Length of the response, or 0 if missing
+    content_length: u64,
+
+    /// number of words found in the response body
+    word_count: usize,
+
+    /// number of lines found in the response body
+    line_count: usize,
+
+    /// recursion depth of the url relative to the scan's starting point
+    depth: usize,
+}
+
+impl FeroxResponse {
+    /// Build a [FeroxResponse](struct.FeroxResponse.html) from a reqwest
+    /// [Response](../reqwest/struct.Response.html), consuming its body in the process
+    ///
+    /// `base_depth` is subtracted off so `depth` reflects recursion depth relative to the
+    /// scan's starting point, not the url's absolute path depth
+    async fn from(response: Response, base_depth: usize) -> Self {
+        let url = response.url().to_string();
+        let status = response.status().as_u16();
+        let content_length = response.content_length().unwrap_or(0);
+        let depth = get_current_depth(&url) - base_depth;
+        let parent = get_parent_directory(&url);
+
+        let body = response.text().await.unwrap_or_default();
+
+        FeroxResponse {
+            url,
+            parent,
+            status,
+            content_length,
+            word_count: body.split_whitespace().count(),
+            line_count: body.lines().count(),
+            depth,
+        }
+    }
+}
+
+/// Helper function that returns the parent directory of the given url's path
+///
+/// example: `http://localhost/a/b/c` -> `http://localhost/a/b/`
+fn get_parent_directory(url: &str) -> String {
+    match Url::parse(url) {
+        Ok(parsed) => {
+            let mut cloned = parsed.clone();
+
+            {
+                let mut segments = match cloned.path_segments_mut() {
+                    Ok(segments) => segments,
+                    Err(_) => return parsed.to_string(),
+                };
+                segments.pop_if_empty().pop().push("");
+            }
+
+            cloned.to_string()
+        }
+        Err(e) => {
+            log::error!("could not parse {} as a Url: {}", url, e);
+            url.to_string()
+        }
+    }
+}
 
 /// Spawn a single consumer task (sc side of mpsc)
 ///
 /// The consumer simply receives responses and writes them to the given output file if they meet
 /// the given reporting criteria
-async fn spawn_file_reporter(mut report_channel: UnboundedReceiver<Response>) {
-    log::trace!("enter: spawn_file_reporter({:?}", report_channel);
+async fn spawn_file_reporter(mut report_channel: UnboundedReceiver<Response>, base_depth: usize) {
+    log::trace!("enter: spawn_file_reporter({:?}, {})", report_channel, base_depth);
 
     log::info!("Writing scan results to {}", CONFIGURATION.output);
 
@@ -37,11 +131,23 @@ async fn spawn_file_reporter(mut report_channel: UnboundedReceiver<Response>) {
             let mut writer = io::BufWriter::new(outfile); // tokio BufWriter
 
             while let Some(resp) = report_channel.recv().await {
-                log::debug!("received {} on reporting channel", resp.url());
+                let url = resp.url().to_string(); // grabbed up front since the json arm below moves resp
+
+                log::debug!("received {} on reporting channel", url);
 
                 if CONFIGURATION.statuscodes.contains(&resp.status().as_u16()) {
-                    let report = if CONFIGURATION.quiet {
-                        format!("{}\n", resp.url())
+                    let report = if CONFIGURATION.json {
+                        let ferox_response = FeroxResponse::from(resp, base_depth).await;
+
+                        match serde_json::to_string(&ferox_response) {
+                            Ok(as_json) => format!("{}\n", as_json),
+                            Err(e) => {
+                                log::error!("could not serialize response as json: {}", e);
+                                continue;
+                            }
+                        }
+                    } else if CONFIGURATION.quiet {
+                        format!("{}\n", url)
                     } else {
                         // example output
                         // 200       3280 https://localhost.com/FAQ
@@ -49,7 +155,7 @@ async fn spawn_file_reporter(mut report_channel: UnboundedReceiver<Response>) {
                             "{} {:>10} {}\n",
                             resp.status().as_str(),
                             resp.content_length().unwrap_or(0),
-                            resp.url()
+                            url
                         )
                     };
 
@@ -72,7 +178,7 @@ async fn spawn_file_reporter(mut report_channel: UnboundedReceiver<Response>) {
                     }
                 }
 
-                log::debug!("report complete: {}", resp.url());
+                log::debug!("report complete: {}", url);
             }
         }
         Err(e) => {
@@ -87,14 +193,27 @@ async fn spawn_file_reporter(mut report_channel: UnboundedReceiver<Response>) {
 ///
 /// The consumer simply receives responses and prints them if they meet the given
 /// reporting criteria
-async fn spawn_terminal_reporter(mut report_channel: UnboundedReceiver<Response>) {
-    log::trace!("enter: spawn_terminal_reporter({:?})", report_channel);
+async fn spawn_terminal_reporter(mut report_channel: UnboundedReceiver<Response>, base_depth: usize) {
+    log::trace!("enter: spawn_terminal_reporter({:?}, {})", report_channel, base_depth);
 
     while let Some(resp) = report_channel.recv().await {
         log::debug!("received {} on reporting channel", resp.url());
 
         if CONFIGURATION.statuscodes.contains(&resp.status().as_u16()) {
-            if CONFIGURATION.quiet {
+            if CONFIGURATION.json {
+                let url = resp.url().to_string();
+                let ferox_response = FeroxResponse::from(resp, base_depth).await;
+
+                match serde_json::to_string(&ferox_response) {
+                    Ok(as_json) => ferox_print(&as_json, &PROGRESS_PRINTER),
+                    Err(e) => {
+                        log::error!("could not serialize response as json: {}", e);
+                    }
+                }
+
+                log::debug!("report complete: {}", url);
+                continue;
+            } else if CONFIGURATION.quiet {
                 ferox_print(&format!("{}", resp.url()), &PROGRESS_PRINTER);
             } else {
                 let status = status_colorizer(&resp.status().as_str());
@@ -123,12 +242,16 @@ fn spawn_recursion_handler(
     mut recursion_channel: UnboundedReceiver<String>,
     wordlist: Arc<HashSet<String>>,
     base_depth: usize,
+    rate_limiter: Arc<RateLimiter>,
+    resume_state: Option<Arc<tokio::sync::Mutex<ScanState>>>,
+    proxy_pool: Option<Arc<ProxyPool>>,
 ) -> BoxFuture<'static, Vec<JoinHandle<()>>> {
     log::trace!(
-        "enter: spawn_recursion_handler({:?}, wordlist[{} words...], {})",
+        "enter: spawn_recursion_handler({:?}, wordlist[{} words...], {}, {:?})",
         recursion_channel,
         wordlist.len(),
-        base_depth
+        base_depth,
+        rate_limiter
     );
 
     let boxed_future = async move {
@@ -137,8 +260,19 @@ fn spawn_recursion_handler(
             log::info!("received {} on recursion channel", resp);
             let clonedresp = resp.clone();
             let clonedlist = wordlist.clone();
+            let cloned_limiter = rate_limiter.clone();
+            let cloned_state = resume_state.clone();
+            let cloned_pool = proxy_pool.clone();
             scans.push(tokio::spawn(async move {
-                scan_url(clonedresp.to_owned().as_str(), clonedlist, base_depth).await
+                scan_url(
+                    clonedresp.to_owned().as_str(),
+                    clonedlist,
+                    base_depth,
+                    cloned_limiter,
+                    cloned_state,
+                    cloned_pool,
+                )
+                .await
             }));
         }
         scans
@@ -320,6 +454,105 @@ async fn try_recursion(
     log::trace!("exit: try_recursion");
 }
 
+/// Determines whether a given status code is worth retrying, i.e. it's likely transient
+///
+/// 429 (rate limited), 502/503/504 (upstream/gateway trouble) are all considered retryable
+fn is_retryable_status(status: StatusCode) -> bool {
+    matches!(
+        status,
+        StatusCode::TOO_MANY_REQUESTS
+            | StatusCode::BAD_GATEWAY
+            | StatusCode::SERVICE_UNAVAILABLE
+            | StatusCode::GATEWAY_TIMEOUT
+    )
+}
+
+/// Computes a full-jitter exponential backoff duration for the given (0-indexed) attempt number
+///
+/// sleeps a random duration in the half-open range `[0, min(cap, base * 2^attempt))`
+fn backoff_duration(attempt: u32) -> Duration {
+    let capped = RETRY_BACKOFF_BASE
+        .checked_mul(2u32.saturating_pow(attempt))
+        .unwrap_or(RETRY_BACKOFF_CAP)
+        .min(RETRY_BACKOFF_CAP);
+
+    let capped_millis = capped.as_millis() as u64;
+
+    let jittered_millis = if capped_millis == 0 {
+        0
+    } else {
+        rand::thread_rng().gen_range(0, capped_millis)
+    };
+
+    Duration::from_millis(jittered_millis)
+}
+
+/// Parses a `Retry-After` header value, which per RFC 7231 is either a number of delta-seconds
+/// or an HTTP-date
+fn parse_retry_after(value: &str) -> Option<Duration> {
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+
+    httpdate::parse_http_date(value).ok().map(|when| {
+        // a date already in the past just means "don't wait", not a parse failure
+        when.duration_since(std::time::SystemTime::now())
+            .unwrap_or(Duration::from_secs(0))
+    })
+}
+
+/// Wrapper for [make_request](fn.make_request.html)
+///
+/// Retries the request up to `CONFIGURATION.retries` times on connection errors, timeouts, or a
+/// retryable status code, sleeping between attempts using full-jitter exponential backoff. When
+/// present, a response's `Retry-After` header is honored as a floor on the sleep duration.
+async fn make_request_with_retries(client: &reqwest::Client, url: &Url) -> Result<Response, reqwest::Error> {
+    log::trace!("enter: make_request_with_retries({:?}, {})", client, url);
+
+    let mut attempt = 0;
+
+    loop {
+        feroxmetrics::record_request();
+
+        let result = make_request(client, url).await;
+
+        let should_retry = match &result {
+            Ok(response) => is_retryable_status(response.status()),
+            Err(e) => e.is_connect() || e.is_timeout(),
+        };
+
+        if !should_retry || attempt >= CONFIGURATION.retries {
+            log::trace!("exit: make_request_with_retries -> {:?}", result);
+            return result;
+        }
+
+        let mut sleep_duration = backoff_duration(attempt);
+
+        if let Ok(response) = &result {
+            if let Some(retry_after) = response
+                .headers()
+                .get(reqwest::header::RETRY_AFTER)
+                .and_then(|value| value.to_str().ok())
+                .and_then(parse_retry_after)
+            {
+                sleep_duration = sleep_duration.max(retry_after);
+            }
+        }
+
+        log::debug!(
+            "retrying {} in {:?} (attempt {}/{})",
+            url,
+            sleep_duration,
+            attempt + 1,
+            CONFIGURATION.retries
+        );
+
+        delay_for(sleep_duration).await;
+
+        attempt += 1;
+    }
+}
+
 /// Wrapper for [make_request](fn.make_request.html)
 ///
 /// Handles making multiple requests based on the presence of extensions
@@ -332,25 +565,89 @@ async fn make_requests(
     filter: Arc<WildcardFilter>,
     dir_chan: UnboundedSender<String>,
     report_chan: UnboundedSender<Response>,
+    rate_limiter: Arc<RateLimiter>,
+    resume_state: Option<Arc<tokio::sync::Mutex<ScanState>>>,
+    proxy_pool: Option<Arc<ProxyPool>>,
 ) {
     log::trace!(
-        "enter: make_requests({}, {}, {}, {:?}, {:?})",
+        "enter: make_requests({}, {}, {}, {:?}, {:?}, {:?})",
         target_url,
         word,
         base_depth,
         dir_chan,
-        report_chan
+        report_chan,
+        rate_limiter
     );
 
+    if let Some(state) = &resume_state {
+        if state.lock().await.is_word_done(target_url, word) {
+            log::debug!("{}/{} already completed, skipping (resumed scan)", target_url, word);
+            return;
+        }
+    }
+
     let urls = create_urls(&target_url, &word, &CONFIGURATION.extensions);
 
+    // tracks whether every url variant (base + extensions) for this word got a response, so a
+    // word that exhausted its retries isn't marked done below and silently skipped on resume
+    let mut all_requests_succeeded = true;
+
     for url in urls {
-        if let Ok(response) = make_request(&CONFIGURATION.client, &url).await {
+        rate_limiter.acquire().await;
+
+        // rotate through the proxy pool (if configured) instead of always using the shared
+        // client, so scan traffic is spread across multiple egress IPs
+        let rotation = match &proxy_pool {
+            Some(pool) => match pool.next_client().await {
+                rotation @ Some(_) => rotation,
+                None => {
+                    // every proxy is quarantined; skip this url rather than silently falling
+                    // back to the direct client, which would leak the real egress IP and defeat
+                    // the point of --proxy-list. leaving this word unmarked lets --resume-from
+                    // pick it back up once a proxy has recovered.
+                    log::warn!(
+                        "no healthy proxy available, skipping {} rather than bypassing the pool",
+                        url
+                    );
+                    all_requests_succeeded = false;
+                    continue;
+                }
+            },
+            None => None,
+        };
+
+        let (client, proxy_idx) = match &rotation {
+            Some((idx, client)) => (client, Some(*idx)),
+            None => (&CONFIGURATION.client, None),
+        };
+
+        let result = make_request_with_retries(client, &url).await;
+
+        if let (Some(pool), Some(idx)) = (&proxy_pool, proxy_idx) {
+            pool.report_outcome(idx, result.is_ok()).await;
+        }
+
+        if result.is_err() {
+            all_requests_succeeded = false;
+        }
+
+        if let Ok(response) = result {
             // response came back without error
 
+            counter!(
+                feroxmetrics::RESPONSES_TOTAL,
+                1,
+                "class" => feroxmetrics::status_class(response.status().as_u16())
+            );
+
             // do recursion if appropriate
             if !CONFIGURATION.norecursion && response_is_directory(&response) {
                 try_recursion(&response, base_depth, dir_chan.clone()).await;
+
+                if !reached_max_depth(response.url(), base_depth) {
+                    // try_recursion only queues directories that are still under the max depth
+                    counter!(feroxmetrics::DIRECTORIES_QUEUED_TOTAL, 1);
+                }
             }
 
             // purposefully doing recursion before filtering. the thought process is that
@@ -361,6 +658,7 @@ async fn make_requests(
             if CONFIGURATION.sizefilters.contains(content_len) {
                 // filtered value from --sizefilters, move on to the next url
                 log::debug!("size filter: filtered out {}", response.url());
+                counter!(feroxmetrics::FILTERED_TOTAL, 1, "filter" => "size");
                 continue;
             }
 
@@ -368,6 +666,7 @@ async fn make_requests(
                 // static wildcard size found during testing
                 // size isn't default, size equals response length, and auto-filter is on
                 log::debug!("static wildcard: filtered out {}", response.url());
+                counter!(feroxmetrics::FILTERED_TOTAL, 1, "filter" => "static_wildcard");
                 continue;
             }
 
@@ -383,6 +682,7 @@ async fn make_requests(
 
                 if url_len + filter.dynamic == *content_len {
                     log::debug!("dynamic wildcard: filtered out {}", response.url());
+                    counter!(feroxmetrics::FILTERED_TOTAL, 1, "filter" => "dynamic_wildcard");
                     continue;
                 }
             }
@@ -398,20 +698,68 @@ async fn make_requests(
             }
         }
     }
+
+    if let Some(state) = &resume_state {
+        if all_requests_succeeded {
+            state.lock().await.mark_word_done(target_url, word);
+        } else {
+            log::debug!(
+                "{}/{} had at least one failed request, leaving unmarked for resume",
+                target_url,
+                word
+            );
+        }
+    }
+
     log::trace!("exit: make_requests");
 }
 
 /// Scan a given url using a given wordlist
 ///
 /// This is the primary entrypoint for the scanner
-pub async fn scan_url(target_url: &str, wordlist: Arc<HashSet<String>>, base_depth: usize) {
+pub async fn scan_url(
+    target_url: &str,
+    wordlist: Arc<HashSet<String>>,
+    base_depth: usize,
+    rate_limiter: Arc<RateLimiter>,
+    resume_state: Option<Arc<tokio::sync::Mutex<ScanState>>>,
+    proxy_pool: Option<Arc<ProxyPool>>,
+) {
     log::trace!(
-        "enter: scan_url({:?}, wordlist[{} words...], {})",
+        "enter: scan_url({:?}, wordlist[{} words...], {}, {:?})",
         target_url,
         wordlist.len(),
-        base_depth
+        base_depth,
+        rate_limiter
     );
 
+    // on the initial (non-recursive) call, build the resume state ourselves if the caller
+    // didn't already wire one up, so `--resume-from` always goes through the hash check in
+    // load_or_create instead of blindly trusting whatever was passed in
+    let resume_state = if resume_state.is_none()
+        && !CONFIGURATION.resume_from.is_empty()
+        && get_current_depth(&target_url) - base_depth == 0
+    {
+        let config_hash = resume::hash_config(&wordlist, &CONFIGURATION.extensions);
+        let state = resume::load_or_create(&CONFIGURATION.resume_from, &config_hash).await;
+        Some(Arc::new(tokio::sync::Mutex::new(state)))
+    } else {
+        resume_state
+    };
+
+    if let Some(state) = &resume_state {
+        let mut locked = state.lock().await;
+
+        if locked.is_dispatched(target_url) && locked.is_directory_complete(target_url, &wordlist)
+        {
+            log::info!("skipping already-completed directory (resumed scan): {}", target_url);
+            log::trace!("exit: scan_url");
+            return;
+        }
+
+        locked.mark_dispatched(target_url);
+    }
+
     log::info!("Starting scan against: {}", target_url);
 
     let (tx_rpt, rx_rpt): (UnboundedSender<Response>, UnboundedReceiver<Response>) =
@@ -434,25 +782,68 @@ pub async fn scan_url(target_url: &str, wordlist: Arc<HashSet<String>>, base_dep
         // join can only be called once, otherwise it causes the thread to panic
         // when current depth - base depth equals zero, we're in the first call to scan_url
         tokio::task::spawn_blocking(move || PROGRESS_BAR.join().unwrap());
+
+        if let Some(port) = CONFIGURATION.metrics_port {
+            // only need to stand the exporter up once, on the initial (non-recursive) call
+            feroxmetrics::start_metrics_server(port);
+        }
+
+        if let Some(state) = resume_state.clone() {
+            if !CONFIGURATION.resume_from.is_empty() {
+                let statefile = CONFIGURATION.resume_from.clone();
+
+                tokio::spawn(async move {
+                    let mut interval = tokio::time::interval(std::time::Duration::from_secs(5));
+
+                    loop {
+                        interval.tick().await;
+
+                        // serialize while holding the lock, then drop it before the disk write
+                        // so make_requests tasks locking the same state aren't stalled for the
+                        // duration of the write
+                        let serialized = resume::serialize(&*state.lock().await);
+
+                        if let Err(e) = fs::write(&statefile, serialized).await {
+                            log::error!("could not persist scan state to {}: {}", statefile, e);
+                        }
+                    }
+                });
+            }
+        }
     }
 
+    gauge!(
+        feroxmetrics::CURRENT_DEPTH,
+        (get_current_depth(&target_url) - base_depth) as f64
+    );
+
     let wildcard_bar = progress_bar.clone();
 
     let reporter = if !CONFIGURATION.output.is_empty() {
         // output file defined
-        tokio::spawn(async move { spawn_file_reporter(rx_rpt).await })
+        tokio::spawn(async move { spawn_file_reporter(rx_rpt, base_depth).await })
     } else {
-        tokio::spawn(async move { spawn_terminal_reporter(rx_rpt).await })
+        tokio::spawn(async move { spawn_terminal_reporter(rx_rpt, base_depth).await })
     };
 
     // lifetime satisfiers, as it's an Arc, clones are cheap anyway
     let looping_words = wordlist.clone();
     let recurser_words = wordlist.clone();
-
-    let recurser =
-        tokio::spawn(
-            async move { spawn_recursion_handler(rx_dir, recurser_words, base_depth).await },
-        );
+    let recurser_limiter = rate_limiter.clone();
+    let recurser_state = resume_state.clone();
+    let recurser_pool = proxy_pool.clone();
+
+    let recurser = tokio::spawn(async move {
+        spawn_recursion_handler(
+            rx_dir,
+            recurser_words,
+            base_depth,
+            recurser_limiter,
+            recurser_state,
+            recurser_pool,
+        )
+        .await
+    });
 
     let filter = match heuristics::wildcard_test(&target_url, wildcard_bar).await {
         Some(f) => {
@@ -474,9 +865,15 @@ pub async fn scan_url(target_url: &str, wordlist: Arc<HashSet<String>>, base_dep
             let txr = tx_rpt.clone();
             let pb = progress_bar.clone(); // progress bar is an Arc around internal state
             let tgt = target_url.to_string(); // done to satisfy 'static lifetime below
+            let limiter = rate_limiter.clone();
+            let state = resume_state.clone();
+            let pool = proxy_pool.clone();
             (
                 tokio::spawn(async move {
-                    make_requests(&tgt, &word, base_depth, wc_filter, txd, txr).await
+                    make_requests(
+                        &tgt, &word, base_depth, wc_filter, txd, txr, limiter, state, pool,
+                    )
+                    .await
                 }),
                 pb,
             )
@@ -527,6 +924,75 @@ pub async fn scan_url(target_url: &str, wordlist: Arc<HashSet<String>>, base_dep
 mod tests {
     use super::*;
 
+    #[test]
+    /// a url with multiple path segments should have its last segment stripped off, leaving a
+    /// trailing slash behind
+    fn get_parent_directory_strips_last_segment() {
+        assert_eq!(
+            get_parent_directory("http://localhost/a/b/c"),
+            "http://localhost/a/b/"
+        );
+    }
+
+    #[test]
+    /// a url that's already a directory (ends with /) should have its own parent returned
+    fn get_parent_directory_of_directory_strips_its_own_segment() {
+        assert_eq!(
+            get_parent_directory("http://localhost/a/b/"),
+            "http://localhost/a/"
+        );
+    }
+
+    #[test]
+    /// 429/502/503/504 are retryable, everything else tested here is not
+    fn is_retryable_status_identifies_expected_codes() {
+        assert!(is_retryable_status(StatusCode::TOO_MANY_REQUESTS));
+        assert!(is_retryable_status(StatusCode::BAD_GATEWAY));
+        assert!(is_retryable_status(StatusCode::SERVICE_UNAVAILABLE));
+        assert!(is_retryable_status(StatusCode::GATEWAY_TIMEOUT));
+        assert!(!is_retryable_status(StatusCode::OK));
+        assert!(!is_retryable_status(StatusCode::NOT_FOUND));
+        assert!(!is_retryable_status(StatusCode::INTERNAL_SERVER_ERROR));
+    }
+
+    #[test]
+    /// backoff_duration should never exceed the configured cap, even for large attempt numbers
+    fn backoff_duration_never_exceeds_cap() {
+        for attempt in 0..10 {
+            assert!(backoff_duration(attempt) <= RETRY_BACKOFF_CAP);
+        }
+    }
+
+    #[test]
+    /// backoff_duration's range is half-open, so it should never equal the cap exactly
+    fn backoff_duration_is_half_open() {
+        // attempt large enough that base * 2^attempt is well beyond the cap, so every sample
+        // is drawn from [0, cap)
+        for _ in 0..100 {
+            assert_ne!(backoff_duration(10), RETRY_BACKOFF_CAP);
+        }
+    }
+
+    #[test]
+    /// a delta-seconds Retry-After value should be parsed directly
+    fn parse_retry_after_accepts_delta_seconds() {
+        assert_eq!(parse_retry_after("120"), Some(Duration::from_secs(120)));
+    }
+
+    #[test]
+    /// an HTTP-date Retry-After value in the past should still parse, yielding a zero duration
+    /// rather than None
+    fn parse_retry_after_accepts_http_date() {
+        let parsed = parse_retry_after("Sun, 06 Nov 1994 08:49:37 GMT");
+        assert_eq!(parsed, Some(Duration::from_secs(0)));
+    }
+
+    #[test]
+    /// garbage input should parse as neither delta-seconds nor an HTTP-date
+    fn parse_retry_after_rejects_garbage() {
+        assert_eq!(parse_retry_after("not a valid value"), None);
+    }
+
     #[test]
     /// sending url + word without any extensions should get back one url with the joined word
     fn create_urls_no_extension_returns_base_url_with_word() {